@@ -0,0 +1,267 @@
+// ASCII armor (RFC 9580, section 6.2) for everything the crate reads and writes.
+//
+// Encoding base64-encodes serialized packets in 64 column lines, with optional
+// `Version:`/`Comment:` headers and an optional CRC24 footer. Decoding accepts
+// every OpenPGP block type, including cleartext signed messages, and is lenient
+// about CRLF line endings and stray whitespace around pasted blocks. A CRC24
+// mismatch is reported rather than rejected, as RFC 9580 asks of receivers.
+
+use std::io::Read;
+
+use pgp::armor::{BlockType, Dearmor, Headers};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::log;
+
+const CLEARTEXT_HEADER: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+const SIGNATURE_HEADER: &str = "-----BEGIN PGP SIGNATURE-----";
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ArmorConfig {
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    // RFC 9580 discourages the CRC24 footer, so it is only written on request
+    #[serde(default)]
+    pub include_checksum: bool,
+}
+
+impl ArmorConfig {
+    fn headers(&self) -> Headers {
+        let mut headers = Headers::new();
+        if let Some(version) = self.version.as_ref().filter(|v| !v.is_empty()) {
+            headers.insert("Version".to_string(), vec![version.clone()]);
+        }
+        if let Some(comment) = self.comment.as_ref().filter(|c| !c.is_empty()) {
+            headers.insert("Comment".to_string(), vec![comment.clone()]);
+        }
+        headers
+    }
+}
+
+pub struct Armored {
+    pub block_type: BlockType,
+    pub headers: Headers,
+    // Packet bytes; for a cleartext signed message, the signature packets
+    pub data: Vec<u8>,
+    // The signed text of a cleartext signed message, with dash-escaping removed
+    pub cleartext: Option<String>,
+    // Whether the CRC24 footer matched the data, None if there was none
+    pub checksum_valid: Option<bool>,
+}
+
+// Already serialized packets, handed to the rPGP armor writer as-is
+struct Packets<'a>(&'a [u8]);
+
+impl pgp::ser::Serialize for Packets<'_> {
+    fn to_writer<W: std::io::Write>(&self, writer: &mut W) -> pgp::errors::Result<()> {
+        writer.write_all(self.0)?;
+        Ok(())
+    }
+
+    fn write_len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+pub fn encode(block_type: BlockType, packets: &[u8], config: &ArmorConfig) -> Result<String, Error> {
+    let headers = config.headers();
+    let mut armored = Vec::new();
+    pgp::armor::write(
+        &Packets(packets),
+        block_type,
+        &mut armored,
+        Some(&headers),
+        config.include_checksum,
+    )
+    .map_err(|e| Error::Internal(format!("Failed to armor {}: {}", block_type, e)))?;
+
+    String::from_utf8(armored)
+        .map_err(|e| Error::Internal(format!("Failed to armor {}: {}", block_type, e)))
+}
+
+pub fn decode(input: &str) -> Result<Armored, Error> {
+    let input = input.trim();
+    if input.starts_with(CLEARTEXT_HEADER) {
+        return decode_cleartext(input);
+    }
+
+    dearmor(&normalize(input))
+}
+
+// Decode a block and check that it holds what the caller expects
+pub fn decode_as(input: &str, expected: BlockType) -> Result<Vec<u8>, Error> {
+    let armored = decode(input)?;
+    if armored.block_type != expected {
+        return Err(Error::MalformedArmor(format!(
+            "Malformed armor: expected {}, found {}",
+            expected, armored.block_type
+        )));
+    }
+    Ok(armored.data)
+}
+
+// Strip CRs and the whitespace around each line, which base64 and armor
+// headers never contain but copy and paste often adds
+fn normalize(input: &str) -> String {
+    let mut normalized = String::with_capacity(input.len());
+    for line in input.lines() {
+        normalized.push_str(line.trim());
+        normalized.push('\n');
+    }
+    normalized
+}
+
+// The CRC24 footer is optional and, when present, only advisory: RFC 9580
+// has receivers ignore it, so a mismatch is logged and reported in
+// `checksum_valid`. The check is done here rather than with
+// `DearmorOptions::enable_crc24_check`, which in rPGP 0.21 compares against a
+// hasher that never sees the decoded data
+fn dearmor(input: &str) -> Result<Armored, Error> {
+    let mut dearmor = Dearmor::new(input.as_bytes());
+    let mut data = Vec::new();
+    dearmor
+        .read_to_end(&mut data)
+        .map_err(|e| Error::MalformedArmor(format!("Malformed armor: {}", e)))?;
+
+    let block_type = dearmor
+        .typ
+        .ok_or_else(|| Error::MalformedArmor("Malformed armor: missing armor header line".to_string()))?;
+
+    let checksum_valid = dearmor.checksum.map(|checksum| checksum == u64::from(crc24(&data)));
+    if checksum_valid == Some(false) {
+        log(&format!("⚠️ CRC24 checksum mismatch in {}, ignored", block_type));
+    }
+
+    Ok(Armored {
+        block_type,
+        headers: dearmor.headers,
+        data,
+        cleartext: None,
+        checksum_valid,
+    })
+}
+
+// CRC24 as defined in RFC 9580, section 6.1.1
+fn crc24(data: &[u8]) -> u32 {
+    const CRC24_INIT: u32 = 0xB704CE;
+    const CRC24_POLY: u32 = 0x1864CFB;
+
+    let mut crc = CRC24_INIT;
+    for &byte in data {
+        crc ^= u32::from(byte) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xFF_FFFF
+}
+
+// A cleartext signed message is a header block, the dash-escaped text and an
+// armored signature. Only the signature part is base64, so the text is split
+// off first and kept byte for byte apart from line endings
+fn decode_cleartext(input: &str) -> Result<Armored, Error> {
+    let mut lines = input.lines().map(|line| line.trim_end_matches('\r'));
+    lines.next();
+
+    let mut headers = Headers::new();
+    for line in lines.by_ref() {
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| Error::MalformedArmor(format!("Malformed armor: invalid header line: {}", line)))?;
+        headers
+            .entry(key.trim().to_string())
+            .or_default()
+            .push(value.trim().to_string());
+    }
+
+    let mut text = Vec::new();
+    let mut signature = None;
+    for line in lines.by_ref() {
+        // Armor lines start at column 0, so an indented header line is text
+        if line.trim_end() == SIGNATURE_HEADER {
+            signature = Some(line.trim_end());
+            break;
+        }
+        text.push(line.strip_prefix("- ").unwrap_or(line));
+    }
+    let signature =
+        signature.ok_or_else(|| Error::MalformedArmor("Malformed armor: cleartext message has no signature".to_string()))?;
+
+    let signature_block: Vec<&str> = std::iter::once(signature).chain(lines).collect();
+    let signature = dearmor(&normalize(&signature_block.join("\n")))?;
+    if signature.block_type != BlockType::Signature {
+        return Err(Error::MalformedArmor(format!(
+            "Malformed armor: expected {} after signed text, found {}",
+            BlockType::Signature,
+            signature.block_type
+        )));
+    }
+
+    Ok(Armored {
+        block_type: BlockType::CleartextMessage,
+        headers,
+        data: signature.data,
+        cleartext: Some(text.join("\n")),
+        checksum_valid: signature.checksum_valid,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKETS: &[u8] = b"\xc2\x04\x01\x02\x03\x04 not really a packet, armor does not care";
+
+    fn armored(include_checksum: bool) -> String {
+        let config = ArmorConfig {
+            include_checksum,
+            ..ArmorConfig::default()
+        };
+        encode(BlockType::Message, PACKETS, &config).unwrap()
+    }
+
+    #[test]
+    fn decodes_crlf_and_surrounding_whitespace() {
+        let pasted = armored(false)
+            .lines()
+            .map(|line| format!("  {}\t ", line))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        let armored = decode(&format!("\r\n\n{}\r\n\n", pasted)).unwrap();
+
+        assert_eq!(armored.block_type, BlockType::Message);
+        assert_eq!(armored.data, PACKETS);
+        assert_eq!(armored.checksum_valid, None);
+    }
+
+    #[test]
+    fn reports_checksum_mismatch_without_rejecting() {
+        let intact = decode(&armored(true)).unwrap();
+        assert_eq!(intact.checksum_valid, Some(true));
+
+        let tampered = armored(true)
+            .lines()
+            .map(|line| if line.starts_with('=') { "=AAAA" } else { line })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let armored = decode(&tampered).unwrap();
+        assert_eq!(armored.data, PACKETS);
+        assert_eq!(armored.checksum_valid, Some(false));
+    }
+
+    #[test]
+    fn checks_the_expected_block_type() {
+        let err = decode_as(&armored(false), BlockType::Signature).unwrap_err();
+        assert_eq!(err.code(), "MALFORMED_ARMOR");
+    }
+}
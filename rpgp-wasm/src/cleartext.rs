@@ -0,0 +1,197 @@
+// Cleartext signature framework (RFC 9580, section 7).
+//
+// The signed text stays readable in the message, with lines that start with a
+// dash escaped as "- -". Signatures are text signatures over the canonical
+// form of the text: trailing spaces and tabs removed from every line, and
+// lines joined with CRLF, so a message still verifies after mail transport
+// has changed its line endings or padding. This is done here rather than with
+// rPGP's `CleartextSignedMessage`, which in 0.21 hashes the text before
+// trailing whitespace is removed, so such messages never verify.
+
+use pgp::armor::BlockType;
+use pgp::composed::{Deserializable, DetachedSignature};
+use pgp::crypto::hash::HashAlgorithm;
+use pgp::packet::Signature;
+use pgp::ser::Serialize as _;
+use pgp::types::{Password, SigningKey};
+
+use crate::error::Error;
+use crate::armor::{self, Armored};
+use crate::armor_packets;
+
+const HEADER_LINE: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+
+pub struct CleartextMessage {
+    // The signed text with dash-escaping removed
+    pub text: String,
+    pub signatures: Vec<Signature>,
+}
+
+impl CleartextMessage {
+    // The text as it was hashed for the signatures
+    pub fn signed_text(&self) -> String {
+        canonical_text(&self.text)
+    }
+}
+
+fn canonical_text(text: &str) -> String {
+    text.split('\n')
+        .map(|line| line.trim_end_matches([' ', '\t', '\r']))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+pub fn sign(
+    text: &str,
+    key: &impl SigningKey,
+    password: &Password,
+    hash_algorithm: HashAlgorithm,
+) -> Result<String, Error> {
+    let signature = DetachedSignature::sign_text_data(
+        rand::thread_rng(),
+        key,
+        password,
+        hash_algorithm,
+        canonical_text(text).as_bytes(),
+    )
+    .map_err(|e| Error::Internal(format!("Signing failed: {}", e)))?;
+    let signature = signature
+        .to_bytes()
+        .map_err(|e| Error::Internal(format!("Failed to serialize signature: {}", e)))?;
+
+    // The line break after the last line of text separates it from the
+    // signature and is not part of what was signed
+    let mut message = format!("{}\nHash: {}\n\n", HEADER_LINE, hash_algorithm);
+    for line in text.split('\n') {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with('-') {
+            message.push_str("- ");
+        }
+        message.push_str(line);
+        message.push('\n');
+    }
+    message.push_str(&armor_packets(BlockType::Signature, &signature)?);
+    Ok(message)
+}
+
+// Parse a cleartext signed message. Only `Hash` armor headers are allowed, and
+// when present they must name the hash of every signature, since headers
+// outside the signed data could otherwise be used to mislead the reader
+pub fn parse(input: &str) -> Result<CleartextMessage, Error> {
+    from_armored(armor::decode(input)?)
+}
+
+pub fn from_armored(armored: Armored) -> Result<CleartextMessage, Error> {
+    let Some(text) = armored.cleartext else {
+        return Err(Error::MalformedArmor(format!(
+            "Malformed armor: expected {}, found {}",
+            BlockType::CleartextMessage,
+            armored.block_type
+        )));
+    };
+
+    if let Some(name) = armored.headers.keys().find(|name| *name != "Hash") {
+        return Err(Error::MalformedArmor(format!(
+            "Malformed armor: unexpected {} header in a signed message",
+            name
+        )));
+    }
+
+    let signatures: Vec<Signature> = DetachedSignature::from_bytes_many(&armored.data[..])
+        .and_then(|signatures| signatures.map(|s| s.map(|s| s.signature)).collect())
+        .map_err(|e| Error::BadSignature(format!("Invalid signature format: {}", e)))?;
+    if signatures.is_empty() {
+        return Err(Error::BadSignature("Invalid signature format: no signatures".to_string()));
+    }
+
+    if let Some(hashes) = armored.headers.get("Hash") {
+        let declared: Vec<&str> = hashes
+            .iter()
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+        for hash in signatures.iter().filter_map(|signature| signature.hash_alg()) {
+            let name = hash.to_string();
+            if !declared.iter().any(|declared| declared.eq_ignore_ascii_case(&name)) {
+                return Err(Error::MalformedArmor(format!(
+                    "Malformed armor: Hash header does not list {}",
+                    name
+                )));
+            }
+        }
+    }
+
+    Ok(CleartextMessage { text, signatures })
+}
+
+#[cfg(test)]
+mod tests {
+    use pgp::composed::SignedSecretKey;
+
+    use super::*;
+    use crate::{build_secret_key, EncryptionAlgorithm, S2kOptions, SigningAlgorithm};
+
+    fn signing_key() -> SignedSecretKey {
+        build_secret_key(
+            "Alice <alice@example.org>",
+            "",
+            &S2kOptions::default(),
+            SigningAlgorithm::Ed25519,
+            EncryptionAlgorithm::X25519,
+        )
+        .unwrap()
+    }
+
+    fn verifies(key: &SignedSecretKey, message: &CleartextMessage) -> bool {
+        let public_key = key.to_public_key();
+        message
+            .signatures
+            .iter()
+            .all(|signature| signature.verify(&public_key.primary_key, message.signed_text().as_bytes()).is_ok())
+    }
+
+    #[test]
+    fn round_trips_dash_lines_and_trailing_whitespace() {
+        let key = signing_key();
+        let text = "- item\n-----BEGIN PGP SIGNATURE-----\n  -----BEGIN PGP SIGNATURE-----\ntrailing  \t\n--\nlast";
+        let signed = sign(text, &key.primary_key, &Password::empty(), HashAlgorithm::Sha3_256).unwrap();
+        assert!(signed.contains("\n- - item\n- -----BEGIN PGP SIGNATURE-----\n"));
+
+        let message = parse(&signed).unwrap();
+        assert_eq!(message.text, text);
+        assert!(verifies(&key, &message));
+    }
+
+    #[test]
+    fn verifies_after_transport_changes_line_endings_and_padding() {
+        let key = signing_key();
+        let text = "first line\nsecond line   \n";
+        let signed = sign(text, &key.primary_key, &Password::empty(), HashAlgorithm::Sha3_256).unwrap();
+
+        let crlf = signed.replace('\n', "\r\n");
+        let message = parse(&crlf).unwrap();
+        assert_eq!(message.text, text);
+        assert!(verifies(&key, &message));
+
+        let stripped: String = signed.lines().map(|line| format!("{}\n", line.trim_end())).collect();
+        assert!(verifies(&key, &parse(&stripped).unwrap()));
+    }
+
+    #[test]
+    fn rejects_tampered_text() {
+        let key = signing_key();
+        let signed = sign("pay 10 EUR", &key.primary_key, &Password::empty(), HashAlgorithm::Sha3_256).unwrap();
+
+        let message = parse(&signed.replace("10 EUR", "99 EUR")).unwrap();
+        assert!(!verifies(&key, &message));
+    }
+
+    #[test]
+    fn rejects_unknown_armor_headers() {
+        let key = signing_key();
+        let signed = sign("hello", &key.primary_key, &Password::empty(), HashAlgorithm::Sha3_256).unwrap();
+
+        let result = parse(&signed.replacen("Hash:", "Comment: trust me\nHash:", 1));
+        assert_eq!(result.err().map(|err| err.code()), Some("MALFORMED_ARMOR"));
+    }
+}
@@ -0,0 +1,155 @@
+// Errors returned across the wasm boundary. Each variant is thrown as its own
+// JS `Error` subclass carrying a stable `code`, so front ends can branch on
+// `error.code` rather than on message text, which is for humans and may change.
+
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen(inline_js = r#"
+export class PgpError extends Error {
+    constructor(message, code) {
+        super(message);
+        this.code = code;
+    }
+}
+PgpError.prototype.name = "PgpError";
+
+export class BadParamsError extends PgpError {}
+BadParamsError.prototype.name = "BadParamsError";
+export class KeyNotFoundError extends PgpError {}
+KeyNotFoundError.prototype.name = "KeyNotFoundError";
+export class WrongPassphraseError extends PgpError {}
+WrongPassphraseError.prototype.name = "WrongPassphraseError";
+export class MalformedArmorError extends PgpError {}
+MalformedArmorError.prototype.name = "MalformedArmorError";
+export class BadSignatureError extends PgpError {}
+BadSignatureError.prototype.name = "BadSignatureError";
+export class IntegrityError extends PgpError {}
+IntegrityError.prototype.name = "IntegrityError";
+export class UnsupportedAlgorithmError extends PgpError {}
+UnsupportedAlgorithmError.prototype.name = "UnsupportedAlgorithmError";
+export class ExpiredKeyError extends PgpError {}
+ExpiredKeyError.prototype.name = "ExpiredKeyError";
+export class RevokedKeyError extends PgpError {}
+RevokedKeyError.prototype.name = "RevokedKeyError";
+
+const ERROR_CLASSES = {
+    BAD_PARAMS: BadParamsError,
+    KEY_NOT_FOUND: KeyNotFoundError,
+    WRONG_PASSPHRASE: WrongPassphraseError,
+    MALFORMED_ARMOR: MalformedArmorError,
+    BAD_SIGNATURE: BadSignatureError,
+    INTEGRITY_FAILURE: IntegrityError,
+    UNSUPPORTED_ALGORITHM: UnsupportedAlgorithmError,
+    EXPIRED_KEY: ExpiredKeyError,
+    REVOKED_KEY: RevokedKeyError,
+};
+
+export function createError(code, message) {
+    const ErrorClass = ERROR_CLASSES[code] || PgpError;
+    return new ErrorClass(message, code);
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(js_name = createError)]
+    fn create_error(code: &str, message: &str) -> JsValue;
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const PGP_ERROR_TYPES: &str = r#"
+export type PgpErrorCode =
+    | "BAD_PARAMS"
+    | "KEY_NOT_FOUND"
+    | "WRONG_PASSPHRASE"
+    | "MALFORMED_ARMOR"
+    | "BAD_SIGNATURE"
+    | "INTEGRITY_FAILURE"
+    | "UNSUPPORTED_ALGORITHM"
+    | "EXPIRED_KEY"
+    | "REVOKED_KEY"
+    | "INTERNAL";
+
+export interface PgpError extends Error {
+    code: PgpErrorCode;
+}
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    // Unparseable JSON, missing fields or out of range values
+    BadParams(String),
+    // No stored key matches the given fingerprint or key ID, or none of the
+    // stored keys is the one a message was encrypted to
+    KeyNotFound(String),
+    WrongPassphrase(String),
+    // Armor or packet data that cannot be decoded
+    MalformedArmor(String),
+    // A signature that is invalid or cannot be parsed, including self-signatures
+    BadSignature(String),
+    // Ciphertext or session key packets that fail authentication
+    IntegrityFailure(String),
+    UnsupportedAlgorithm(String),
+    ExpiredKey(String),
+    RevokedKey(String),
+    // Failures of the backend or the browser that callers cannot correct
+    Internal(String),
+}
+
+impl Error {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::BadParams(_) => "BAD_PARAMS",
+            Error::KeyNotFound(_) => "KEY_NOT_FOUND",
+            Error::WrongPassphrase(_) => "WRONG_PASSPHRASE",
+            Error::MalformedArmor(_) => "MALFORMED_ARMOR",
+            Error::BadSignature(_) => "BAD_SIGNATURE",
+            Error::IntegrityFailure(_) => "INTEGRITY_FAILURE",
+            Error::UnsupportedAlgorithm(_) => "UNSUPPORTED_ALGORITHM",
+            Error::ExpiredKey(_) => "EXPIRED_KEY",
+            Error::RevokedKey(_) => "REVOKED_KEY",
+            Error::Internal(_) => "INTERNAL",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::BadParams(message)
+            | Error::KeyNotFound(message)
+            | Error::WrongPassphrase(message)
+            | Error::MalformedArmor(message)
+            | Error::BadSignature(message)
+            | Error::IntegrityFailure(message)
+            | Error::UnsupportedAlgorithm(message)
+            | Error::ExpiredKey(message)
+            | Error::RevokedKey(message)
+            | Error::Internal(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for JsValue {
+    fn from(error: Error) -> JsValue {
+        create_error(error.code(), error.message())
+    }
+}
+
+// Exceptions raised by browser APIs, such as IndexedDB
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Error {
+        let message = match value.dyn_ref::<js_sys::Error>() {
+            Some(error) => String::from(error.message()),
+            None => value.as_string().unwrap_or_else(|| format!("{:?}", value)),
+        };
+        Error::Internal(message)
+    }
+}
@@ -0,0 +1,1225 @@
+// A keyring owning its certificates and secret keys. Every instance is
+// independent, so separate profiles or tests never see each other's keys; the
+// free functions exported from the crate root work on a shared default one.
+// Changes are written through to the keyring's `KeyStore`.
+
+use std::collections::HashMap;
+
+use pgp::armor::BlockType;
+use pgp::composed::{
+    Deserializable, DetachedSignature, Message, SignedPublicKey, SignedSecretKey,
+};
+use pgp::crypto::aead::{AeadAlgorithm, ChunkSize};
+use pgp::crypto::sym::SymmetricKeyAlgorithm;
+use pgp::packet::{
+    DataMode, LiteralDataHeader, PacketTrait, PublicKeyEncryptedSessionKey, Signature,
+    SymEncryptedProtectedData,
+};
+use pgp::ser::Serialize as _;
+use pgp::types::{DecryptionKey, EskType, KeyDetails, Password, PkeskVersion};
+use wasm_bindgen::prelude::*;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    all_valid, armor, armor_packets, build_secret_key, cleartext, encryption_subkey, ensure_usable,
+    find_key, format_timestamp, is_protected, key_identifiers, key_info, key_pair_algorithm, literal_content,
+    literal_data_packet, literal_metadata, log, one_pass_signature, parse_public_key,
+    parse_params, parse_secret_key, primary_self_signatures, read_signed_message, recipient_subkey,
+    reprotect_secret_key, retain_self_signatures, signature_hash_algorithm, text_from_crlf, unlock_signing_key,
+    validate_self_signatures, verify_signature, ChangePassphraseParams, DecryptParams,
+    DecryptResult, DecryptedData, EncryptParams, ImportSecretKeyParams, KeyGenerationParams,
+    KeyInfo, KeyPairResult, LiteralFormat, LiteralMetadata, SignParams, SignedMessageResult,
+    VerifiedData, VerifyParams, VerifyResult, VerifySignedMessageParams,
+};
+use crate::error::Error;
+use crate::store::{self, IndexedDbKeyStore, KeyStore, MemoryKeyStore, PendingWrite, StoredKey};
+
+// Content of a signed message, its literal data header if it had one, and
+// the outcome for each signature
+type VerifiedContent = (Vec<u8>, Option<LiteralDataHeader>, Vec<VerifyResult>);
+
+#[wasm_bindgen]
+pub struct Keyring {
+    secret_keys: HashMap<String, SignedSecretKey>,
+    public_keys: HashMap<String, SignedPublicKey>,
+    // Armored secret keys as last written to the store, by fingerprint
+    stored_secret_keys: HashMap<String, String>,
+    store: Box<dyn KeyStore>,
+}
+
+impl Default for Keyring {
+    fn default() -> Self {
+        Keyring::with_store(Box::new(MemoryKeyStore::default()))
+    }
+}
+
+impl Keyring {
+    fn with_store(store: Box<dyn KeyStore>) -> Keyring {
+        Keyring {
+            secret_keys: HashMap::new(),
+            public_keys: HashMap::new(),
+            stored_secret_keys: HashMap::new(),
+            store,
+        }
+    }
+
+    // Add keys read back from a store. Records that no longer parse are
+    // skipped rather than failing the whole keyring
+    fn load(&mut self, records: Vec<StoredKey>) {
+        for record in records {
+            let public_key = match parse_public_key(&record.public_key_armored) {
+                Ok(public_key) => public_key,
+                Err(_) => {
+                    log(&format!("⚠️ Skipping unreadable stored key {}", record.fingerprint));
+                    continue;
+                }
+            };
+            let (_, fingerprint) = key_identifiers(&public_key.primary_key);
+
+            if let Some(armored) = &record.private_key_armored {
+                match parse_secret_key(armored) {
+                    Ok(secret_key) => {
+                        self.secret_keys.insert(fingerprint.clone(), secret_key);
+                        self.stored_secret_keys.insert(fingerprint.clone(), armored.clone());
+                    }
+                    Err(_) => log(&format!("⚠️ Skipping unreadable stored secret key {}", fingerprint)),
+                }
+            }
+            self.public_keys.insert(fingerprint, public_key);
+        }
+    }
+
+    // Write the current state of a key to the store. A secret key is only
+    // written if every one of its packets is encrypted under a passphrase;
+    // otherwise a protected copy stored earlier is kept. Returns whether the
+    // secret key as held is kept in memory only
+    fn persist(&mut self, fingerprint: &str) -> Result<bool, Error> {
+        let Some(public_key) = self.public_keys.get(fingerprint) else {
+            return Ok(false);
+        };
+        let public_key_bytes = public_key
+            .to_bytes()
+            .map_err(|e| Error::Internal(format!("Failed to serialize public key: {}", e)))?;
+
+        let secret_key = self.secret_keys.get(fingerprint);
+        let protected = secret_key.filter(|secret_key| is_protected(secret_key));
+        if secret_key.is_some() && protected.is_none() {
+            log(&format!(
+                "⚠️ Secret key {} is not passphrase protected and is kept in memory only",
+                fingerprint
+            ));
+        }
+        let private_key_armored = match protected {
+            Some(secret_key) => {
+                let private_key_bytes = secret_key.to_bytes().map_err(|e| {
+                    Error::Internal(format!("Failed to serialize private key: {}", e))
+                })?;
+                let armored = armor_packets(BlockType::PrivateKey, &private_key_bytes)?;
+                self.stored_secret_keys.insert(fingerprint.to_string(), armored.clone());
+                Some(armored)
+            }
+            None => self.stored_secret_keys.get(fingerprint).cloned(),
+        };
+        let memory_only = secret_key.is_some() && protected.is_none();
+
+        self.store.put(StoredKey {
+            fingerprint: fingerprint.to_string(),
+            public_key_armored: armor_packets(BlockType::PublicKey, &public_key_bytes)?,
+            private_key_armored,
+        })?;
+        Ok(memory_only)
+    }
+
+    // Writes to the store that may not have been committed yet
+    pub(crate) fn take_pending(&mut self) -> Vec<PendingWrite> {
+        self.store.take_pending()
+    }
+
+    // Take over the keys of another keyring that this one lacks, writing them
+    // to this keyring's store
+    pub(crate) fn merge(&mut self, other: &Keyring) -> Result<(), Error> {
+        for (fingerprint, public_key) in &other.public_keys {
+            if self.public_keys.contains_key(fingerprint) {
+                continue;
+            }
+            self.public_keys.insert(fingerprint.clone(), public_key.clone());
+            if let Some(secret_key) = other.secret_keys.get(fingerprint) {
+                self.secret_keys.insert(fingerprint.clone(), secret_key.clone());
+            }
+            self.persist(fingerprint)?;
+        }
+        Ok(())
+    }
+
+    // Encrypt literal data to every recipient. The body is sealed with
+    // AES-256-OCB (SEIPD v2), no compression is applied so the ciphertext only
+    // reveals the plaintext length
+    fn encrypt(
+        &self,
+        recipient_key_ids: &[String],
+        literal: &LiteralMetadata,
+        data: &[u8],
+        is_text: bool,
+    ) -> Result<String, Error> {
+        if recipient_key_ids.is_empty() {
+            return Err(Error::BadParams("At least one recipient key ID is required".to_string()));
+        }
+
+        let (mode, data) = literal_content(literal, data, is_text)?;
+        let literal_data = literal_data_packet(literal, mode, &data)?;
+
+        let mut rng = rand::thread_rng();
+        let session_key = SymmetricKeyAlgorithm::AES256.new_session_key(&mut rng);
+        let mut ciphertext = Vec::new();
+
+        // The session key is encapsulated separately to every recipient
+        for key_id in recipient_key_ids {
+            let public_key = find_key(&self.public_keys, key_id)
+                .ok_or_else(|| Error::KeyNotFound(format!("Recipient key not found: {}", key_id)))?;
+            ensure_usable(
+                public_key.primary_key.created_at(),
+                &primary_self_signatures(&public_key.details),
+                key_id,
+            )?;
+            let subkey = encryption_subkey(public_key).ok_or_else(|| {
+                Error::KeyNotFound(format!("Recipient key has no encryption subkey: {}", key_id))
+            })?;
+            ensure_usable(subkey.key.created_at(), &subkey.signatures, key_id)?;
+
+            PublicKeyEncryptedSessionKey::from_session_key_v6(&mut rng, &session_key, &subkey.key)
+                .and_then(|pkesk| pkesk.to_writer_with_header(&mut ciphertext))
+                .map_err(|e| Error::Internal(format!("Failed to encrypt session key: {}", e)))?;
+        }
+
+        SymEncryptedProtectedData::encrypt_seipdv2(
+            &mut rng,
+            SymmetricKeyAlgorithm::AES256,
+            AeadAlgorithm::Ocb,
+            ChunkSize::default(),
+            session_key.as_ref(),
+            &literal_data,
+        )
+        .and_then(|seipd| seipd.to_writer_with_header(&mut ciphertext))
+        .map_err(|e| Error::Internal(format!("Failed to encrypt message: {}", e)))?;
+        armor_packets(BlockType::Message, &ciphertext)
+    }
+
+    // Decrypt a message with a stored secret key, returning the literal data
+    // and its header
+    fn decrypt(&self, params: &DecryptParams) -> Result<(LiteralDataHeader, Zeroizing<Vec<u8>>), Error> {
+        let secret_key = find_key(&self.secret_keys, &params.private_key_id)
+            .ok_or_else(|| Error::KeyNotFound(format!("Private key not found: {}", params.private_key_id)))?;
+
+        let ciphertext = armor::decode_as(&params.ciphertext, BlockType::Message)?;
+        let message = Message::from_bytes(std::io::Cursor::new(ciphertext))
+            .map_err(|e| Error::MalformedArmor(format!("Invalid ciphertext format: {}", e)))?;
+
+        let Message::Encrypted { esk, .. } = &message else {
+            return Err(Error::BadParams("Invalid ciphertext format: message is not encrypted".to_string()));
+        };
+
+        let (subkey, pkesk) = recipient_subkey(secret_key, esk).ok_or_else(|| {
+            Error::KeyNotFound(format!("Wrong key: message is not encrypted to {}", params.private_key_id))
+        })?;
+
+        let esk_type = match pkesk.version() {
+            PkeskVersion::V3 => EskType::V3_4,
+            PkeskVersion::V6 => EskType::V6,
+            PkeskVersion::Other(v) => {
+                return Err(Error::UnsupportedAlgorithm(format!("Unsupported session key packet version {}", v)))
+            }
+        };
+        let values = pkesk
+            .values()
+            .map_err(|e| Error::MalformedArmor(format!("Invalid ciphertext format: {}", e)))?;
+
+        // Unlocking the subkey and decapsulating the session key are kept apart
+        // from decrypting the body, so each failure is reported on its own
+        let password = Password::from(params.passphrase.as_str());
+        let session_key = subkey
+            .key
+            .decrypt(&password, values, esk_type)
+            .map_err(|_| Error::WrongPassphrase(format!("Wrong passphrase for key {}", params.private_key_id)))?
+            .map_err(|e| Error::IntegrityFailure(format!("Integrity check failed: bad session key packet: {}", e)))?;
+
+        // The AEAD tags are checked as the body is decrypted and read, so any
+        // failure from here on means the ciphertext was modified
+        let mut decrypted = message
+            .decrypt_with_session_key(session_key)
+            .and_then(|m| m.decompress())
+            .map_err(|e| Error::IntegrityFailure(format!("Integrity check failed: {}", e)))?;
+        let plaintext = decrypted
+            .as_data_vec()
+            .map(Zeroizing::new)
+            .map_err(|e| Error::IntegrityFailure(format!("Integrity check failed: {}", e)))?;
+
+        let header = decrypted
+            .literal_data_header()
+            .cloned()
+            .ok_or_else(|| Error::MalformedArmor("Invalid ciphertext format: no literal data".to_string()))?;
+
+        Ok((header, plaintext))
+    }
+
+    // Look up a signing key and check that it is usable and that the
+    // passphrase unlocks it, so a wrong one is reported as such rather than as
+    // a generic signing failure
+    fn signing_key(&self, params: &SignParams) -> Result<(&SignedSecretKey, Password), Error> {
+        let secret_key = find_key(&self.secret_keys, &params.private_key_id)
+            .ok_or_else(|| Error::KeyNotFound(format!("Private key not found: {}", params.private_key_id)))?;
+        ensure_usable(
+            secret_key.created_at(),
+            &primary_self_signatures(&secret_key.details),
+            &params.private_key_id,
+        )?;
+
+        let password = Password::from(params.passphrase.as_str());
+        unlock_signing_key(secret_key, &password, &params.private_key_id)?;
+        Ok((secret_key, password))
+    }
+
+    // One-pass signed message. Text is signed as a text signature over UTF-8
+    // literal data, anything else as a binary signature over the exact bytes
+    fn sign(&self, params: &SignParams, data: &[u8], is_text: bool) -> Result<String, Error> {
+        let (secret_key, password) = self.signing_key(params)?;
+
+        let (mode, data) = literal_content(&params.literal, data, is_text)?;
+        let literal_data = literal_data_packet(&params.literal, mode, &data)?;
+
+        // The signature covers the literal data but not its file name or date
+        let hash_algorithm = signature_hash_algorithm(&secret_key.primary_key);
+        let signature = match mode {
+            DataMode::Utf8 => DetachedSignature::sign_text_data(
+                rand::thread_rng(),
+                &secret_key.primary_key,
+                &password,
+                hash_algorithm,
+                &data[..],
+            ),
+            _ => DetachedSignature::sign_binary_data(
+                rand::thread_rng(),
+                &secret_key.primary_key,
+                &password,
+                hash_algorithm,
+                &data[..],
+            ),
+        }
+        .map_err(|e| Error::Internal(format!("Signing failed: {}", e)))?
+        .signature;
+
+        let mut signed_message = Vec::new();
+        one_pass_signature(&signature, &secret_key.primary_key)?
+            .to_writer_with_header(&mut signed_message)
+            .map_err(|e| Error::Internal(format!("Signing failed: {}", e)))?;
+        signed_message.extend_from_slice(&literal_data);
+        signature
+            .to_writer_with_header(&mut signed_message)
+            .map_err(|e| Error::Internal(format!("Failed to serialize signature: {}", e)))?;
+        armor_packets(BlockType::Message, &signed_message)
+    }
+
+    // Binary signature over the exact bytes, so it can be checked with any
+    // OpenPGP implementation against the file as distributed
+    fn detached_signature(&self, params: &SignParams, data: &[u8]) -> Result<String, Error> {
+        let (secret_key, password) = self.signing_key(params)?;
+
+        let hash_algorithm = signature_hash_algorithm(&secret_key.primary_key);
+        let signature = DetachedSignature::sign_binary_data(
+            rand::thread_rng(),
+            &secret_key.primary_key,
+            &password,
+            hash_algorithm,
+            data,
+        )
+        .map_err(|e| Error::Internal(format!("Signing failed: {}", e)))?;
+
+        let signature = signature
+            .to_bytes()
+            .map_err(|e| Error::Internal(format!("Failed to serialize signature: {}", e)))?;
+        armor_packets(BlockType::Signature, &signature)
+    }
+
+    fn verify(&self, params: &VerifyParams, data: &[u8]) -> Result<VerifyResult, Error> {
+        let signature = armor::decode_as(&params.signature, BlockType::Signature)?;
+        let signature = DetachedSignature::from_bytes(&signature[..])
+            .map_err(|e| Error::BadSignature(format!("Invalid signature format: {}", e)))?;
+
+        let result = verify_signature(&self.public_keys, params.signer_key_id.as_deref(), &signature.signature, data);
+        log(&result.message);
+        Ok(result)
+    }
+
+    // Check every signature of a message over the data it signs
+    fn verify_all(
+        &self,
+        signed_data: &[u8],
+        signatures: &[Signature],
+        signer_key_id: Option<&str>,
+    ) -> Vec<VerifyResult> {
+        let signatures: Vec<VerifyResult> = signatures
+            .iter()
+            .map(|signature| verify_signature(&self.public_keys, signer_key_id, signature, signed_data))
+            .collect();
+        for signature in &signatures {
+            log(&signature.message);
+        }
+        signatures
+    }
+
+    // Read a cleartext signed or inline signed message and check its
+    // signatures
+    fn verify_signed(
+        &self,
+        params: &VerifySignedMessageParams,
+    ) -> Result<VerifiedContent, Error> {
+        let armored = armor::decode(&params.signed_message)?;
+        match armored.block_type {
+            BlockType::CleartextMessage => {
+                let message = cleartext::from_armored(armored)?;
+                let signed_text = message.signed_text();
+                let signatures = self.verify_all(
+                    signed_text.as_bytes(),
+                    &message.signatures,
+                    params.signer_key_id.as_deref(),
+                );
+                Ok((message.text.into_bytes(), None, signatures))
+            }
+            BlockType::Message => {
+                let (header, content, signatures) = read_signed_message(armored.data)?;
+                let signatures = self.verify_all(&content, &signatures, params.signer_key_id.as_deref());
+                Ok((content, Some(header), signatures))
+            }
+            other => Err(Error::MalformedArmor(format!(
+                "Malformed armor: expected {} or {}, found {}",
+                BlockType::CleartextMessage,
+                BlockType::Message,
+                other
+            ))),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Keyring {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Keyring {
+        Keyring::default()
+    }
+
+    // A keyring backed by the named IndexedDB database, loaded with the keys
+    // it already holds
+    pub async fn open_indexed_db(db_name: String) -> Result<Keyring, Error> {
+        let (store, records) = IndexedDbKeyStore::open(&db_name).await?;
+
+        let mut keyring = Keyring::with_store(Box::new(store));
+        keyring.load(records);
+
+        log(&format!("🗄️ Loaded {} keys from IndexedDB", keyring.public_keys.len()));
+        Ok(keyring)
+    }
+
+    // A promise that resolves once the changes made so far are committed to
+    // the keyring's store, and rejects if IndexedDB aborted one of them
+    pub fn flush(&mut self) -> js_sys::Promise {
+        let pending = self.take_pending();
+        wasm_bindgen_futures::future_to_promise(async move {
+            store::committed(pending).await?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    pub fn generate_key_pair(&mut self, params_json: String) -> Result<String, Error> {
+        log("🔑 Generating key pair...");
+    
+        let params: KeyGenerationParams = parse_params(params_json)?;
+    
+        let start_time = chrono::Utc::now();
+
+        let secret_key = build_secret_key(
+            &params.user_id,
+            &params.passphrase,
+            &params.s2k,
+            params.signing_algorithm,
+            params.encryption_algorithm,
+        )?;
+        let public_key = secret_key.to_public_key();
+
+        let (key_id, fingerprint) = key_identifiers(&secret_key.primary_key);
+        let created_at = format_timestamp(secret_key.created_at());
+        let algorithm = key_pair_algorithm(&public_key);
+
+        let private_key_bytes = secret_key
+            .to_bytes()
+            .map_err(|e| Error::Internal(format!("Failed to serialize private key: {}", e)))?;
+        let private_key_armored = armor_packets(BlockType::PrivateKey, &private_key_bytes)?;
+        let public_key_bytes = public_key
+            .to_bytes()
+            .map_err(|e| Error::Internal(format!("Failed to serialize public key: {}", e)))?;
+        let public_key_armored = armor_packets(BlockType::PublicKey, &public_key_bytes)?;
+
+        self.secret_keys.insert(fingerprint.clone(), secret_key);
+        self.public_keys.insert(fingerprint.clone(), public_key);
+        let memory_only = self.persist(&fingerprint)?;
+
+        let result = KeyPairResult {
+            key_id,
+            fingerprint,
+            user_id: params.user_id.clone(),
+            algorithm,
+            public_key_armored,
+            private_key_armored,
+            created_at,
+            memory_only,
+        };
+    
+        let elapsed = chrono::Utc::now() - start_time;
+        log(&format!("✅ Key pair generated in {}ms: {}", elapsed.num_milliseconds(), result.algorithm));
+    
+        serde_json::to_string(&result)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    pub fn import_public_key(&mut self, key_data: JsValue) -> Result<String, Error> {
+        log("📥 Importing public key...");
+
+        let mut public_key = match key_data.as_string() {
+            Some(armored) => parse_public_key(&armored)?,
+            None if key_data.is_instance_of::<js_sys::Uint8Array>() => {
+                let data = js_sys::Uint8Array::new(&key_data).to_vec();
+                SignedPublicKey::from_bytes(&data[..])
+                    .map_err(|e| Error::MalformedArmor(format!("Invalid public key: {}", e)))?
+            }
+            None => {
+                return Err(Error::BadParams(
+                    "Invalid parameters: expected an armored string or a Uint8Array"
+                        .to_string(),
+                ))
+            }
+        };
+
+        validate_self_signatures(&mut public_key)?;
+
+        let mut info = key_info(&public_key);
+        info.has_secret_key = self.secret_keys.contains_key(&info.fingerprint);
+        self.public_keys.insert(info.fingerprint.clone(), public_key);
+        info.memory_only = self.persist(&info.fingerprint)?;
+
+        log(&format!("✅ Imported public key {}", info.fingerprint));
+        serde_json::to_string(&info)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    pub fn import_secret_key(&mut self, params_json: String) -> Result<String, Error> {
+        log("📥 Importing secret key...");
+
+        let params: ImportSecretKeyParams = parse_params(params_json)?;
+
+        // The stored secret key keeps the same self-signatures as the
+        // certificate derived from it, without unchecked third-party ones
+        let mut secret_key = parse_secret_key(&params.private_key_armored)?;
+        retain_self_signatures(&mut secret_key.details, &secret_key.primary_key);
+        let mut public_key = secret_key.to_public_key();
+        validate_self_signatures(&mut public_key)?;
+
+        // Unlocking every secret packet catches a wrong passphrase, or packets
+        // protected under different ones, before the key is relied upon
+        if let Some(passphrase) = &params.passphrase {
+            let password = Password::from(passphrase.as_str());
+            let (key_id, _) = key_identifiers(&secret_key.primary_key);
+            let wrong_passphrase = |_| Error::WrongPassphrase(format!("Wrong passphrase for key {}", key_id));
+            let unlock_failed = |e| Error::Internal(format!("Failed to unlock key {}: {}", key_id, e));
+
+            secret_key
+                .primary_key
+                .unlock(&password, |_, _| Ok(()))
+                .map_err(wrong_passphrase)?
+                .map_err(unlock_failed)?;
+            for subkey in &secret_key.secret_subkeys {
+                subkey
+                    .key
+                    .unlock(&password, |_, _| Ok(()))
+                    .map_err(wrong_passphrase)?
+                    .map_err(unlock_failed)?;
+            }
+        }
+
+        let mut info = key_info(&public_key);
+        info.has_secret_key = true;
+        self.secret_keys.insert(info.fingerprint.clone(), secret_key);
+        self.public_keys.insert(info.fingerprint.clone(), public_key);
+        info.memory_only = self.persist(&info.fingerprint)?;
+
+        log(&format!("✅ Imported secret key {}", info.fingerprint));
+        serde_json::to_string(&info)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    pub fn change_passphrase(&mut self, params_json: String) -> Result<String, Error> {
+        log("🔐 Changing secret key passphrase...");
+
+        let params: ChangePassphraseParams = parse_params(params_json)?;
+
+        let mut secret_key = match (&params.private_key_armored, &params.private_key_id) {
+            (Some(armored), _) => parse_secret_key(armored)?,
+            (None, Some(key_id)) => find_key(&self.secret_keys, key_id)
+                .cloned()
+                .ok_or_else(|| Error::KeyNotFound(format!("Private key not found: {}", key_id)))?,
+            (None, None) => {
+                return Err(Error::BadParams(
+                    "Invalid parameters: either private_key_armored or private_key_id is required"
+                        .to_string(),
+                ))
+            }
+        };
+
+        reprotect_secret_key(
+            &mut secret_key,
+            &params.old_passphrase,
+            &params.new_passphrase,
+            &params.s2k,
+        )?;
+
+        let private_key_bytes = secret_key
+            .to_bytes()
+            .map_err(|e| Error::Internal(format!("Failed to serialize private key: {}", e)))?;
+        let private_key_armored = armor_packets(BlockType::PrivateKey, &private_key_bytes)?;
+
+        // Keep a stored copy of the key in step with the one handed back. An
+        // unprotected copy is only for export, such as a backup; the stored
+        // key stays protected so signing and decrypting still need the
+        // passphrase
+        let (_, fingerprint) = key_identifiers(&secret_key.primary_key);
+        if !params.new_passphrase.is_empty() {
+            if let Some(stored) = self.secret_keys.get_mut(&fingerprint) {
+                *stored = secret_key;
+                self.persist(&fingerprint)?;
+            }
+        }
+
+        if params.new_passphrase.is_empty() {
+            log("✅ Passphrase removed from secret key");
+        } else {
+            log("✅ Secret key re-encrypted under the new passphrase");
+        }
+        Ok(private_key_armored)
+    }
+
+    pub fn encrypt_message(&self, params_json: String) -> Result<String, Error> {
+        log("🔒 Encrypting message with post-quantum cryptography...");
+    
+        let params: EncryptParams = parse_params(params_json)?;
+
+        let ciphertext = self.encrypt(
+            &params.recipient_key_ids,
+            &params.literal,
+            params.plaintext.as_bytes(),
+            true,
+        )?;
+    
+        log("✅ Message encrypted successfully");
+        Ok(ciphertext)
+    }
+
+    // Encrypt arbitrary bytes, such as a file, as binary literal data unless
+    // the format says otherwise
+    pub fn encrypt_bytes(&self, params_json: String, data: &[u8]) -> Result<String, Error> {
+        log("🔒 Encrypting data with post-quantum cryptography...");
+
+        let params: EncryptParams = parse_params(params_json)?;
+
+        let ciphertext = self.encrypt(&params.recipient_key_ids, &params.literal, data, false)?;
+
+        log(&format!("✅ {} bytes encrypted successfully", data.len()));
+        Ok(ciphertext)
+    }
+
+    // The plaintext as JSON, together with the file name, modification date
+    // and format it was sent with
+    pub fn decrypt_message(&self, params_json: String) -> Result<String, Error> {
+        log("🔓 Decrypting message with post-quantum cryptography...");
+    
+        let params: DecryptParams = parse_params(params_json)?;
+
+        let (header, mut plaintext) = self.decrypt(&params)?;
+        let plaintext = String::from_utf8(std::mem::take(&mut *plaintext)).map_err(|e| {
+            let error = Error::BadParams(format!(
+                "Decrypted message is not valid UTF-8, use decrypt_bytes: {}",
+                e.utf8_error()
+            ));
+            e.into_bytes().zeroize();
+            error
+        })?;
+        let plaintext = match header.mode() {
+            DataMode::Binary => plaintext,
+            _ => text_from_crlf(plaintext),
+        };
+        let result = DecryptResult {
+            plaintext,
+            literal: literal_metadata(&header),
+        };
+
+        log("✅ Message decrypted successfully");
+        serde_json::to_string(&result)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    // The literal data exactly as it was encrypted, with its metadata; text is
+    // left with CRLF line endings
+    pub fn decrypt_bytes(&self, params_json: String) -> Result<DecryptedData, Error> {
+        log("🔓 Decrypting data with post-quantum cryptography...");
+
+        let params: DecryptParams = parse_params(params_json)?;
+
+        let (header, mut plaintext) = self.decrypt(&params)?;
+        let literal = literal_metadata(&header);
+
+        log(&format!("✅ {} bytes decrypted successfully", plaintext.len()));
+        Ok(DecryptedData {
+            data: std::mem::take(&mut *plaintext),
+            filename: literal.filename,
+            modified_at: literal.modified_at,
+            format: literal.format.unwrap_or(LiteralFormat::Binary).name().to_string(),
+        })
+    }
+
+    pub fn sign_message(&self, params_json: String) -> Result<String, Error> {
+        log("✍️ Signing message with post-quantum cryptography...");
+    
+        let params: SignParams = parse_params(params_json)?;
+
+        let signed_message = self.sign(&params, params.message.as_bytes(), true)?;
+    
+        log("✅ Message signed successfully");
+        Ok(signed_message)
+    }
+
+    pub fn sign_bytes(&self, params_json: String, data: &[u8]) -> Result<String, Error> {
+        log("✍️ Signing data with post-quantum cryptography...");
+
+        let params: SignParams = parse_params(params_json)?;
+
+        let signed_message = self.sign(&params, data, false)?;
+
+        log("✅ Data signed successfully");
+        Ok(signed_message)
+    }
+
+    pub fn create_detached_signature(&self, params_json: String) -> Result<String, Error> {
+        log("✍️ Creating detached signature with post-quantum cryptography...");
+    
+        let params: SignParams = parse_params(params_json)?;
+
+        let signature = self.detached_signature(&params, params.message.as_bytes())?;
+    
+        log("✅ Detached signature created successfully");
+        Ok(signature)
+    }
+
+    pub fn create_detached_signature_bytes(&self, params_json: String, data: &[u8]) -> Result<String, Error> {
+        log("✍️ Creating detached signature with post-quantum cryptography...");
+
+        let params: SignParams = parse_params(params_json)?;
+
+        let signature = self.detached_signature(&params, data)?;
+
+        log("✅ Detached signature created successfully");
+        Ok(signature)
+    }
+
+    // Cleartext signed message: the text stays readable and can be checked by
+    // any OpenPGP implementation
+    pub fn sign_cleartext_message(&self, params_json: String) -> Result<String, Error> {
+        log("✍️ Creating cleartext signed message with post-quantum cryptography...");
+
+        let params: SignParams = parse_params(params_json)?;
+
+        let (secret_key, password) = self.signing_key(&params)?;
+        let hash_algorithm = signature_hash_algorithm(&secret_key.primary_key);
+        let signed_message =
+            cleartext::sign(&params.message, &secret_key.primary_key, &password, hash_algorithm)?;
+
+        log("✅ Cleartext message signed successfully");
+        Ok(signed_message)
+    }
+
+    pub fn verify_cleartext_message(&self, params_json: String) -> Result<String, Error> {
+        log("🔍 Verifying cleartext signed message...");
+
+        let params: VerifySignedMessageParams = parse_params(params_json)?;
+
+        let message = cleartext::parse(&params.signed_message)?;
+        let signed_text = message.signed_text();
+        let signatures = self.verify_all(
+            signed_text.as_bytes(),
+            &message.signatures,
+            params.signer_key_id.as_deref(),
+        );
+        let result = SignedMessageResult {
+            is_valid: all_valid(&signatures),
+            content: message.text,
+            signatures,
+        };
+
+        serde_json::to_string(&result)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    // Verify a complete signed message, either cleartext signed or an inline
+    // signed OpenPGP message such as the output of `sign_message`
+    pub fn verify_signed_message(&self, params_json: String) -> Result<String, Error> {
+        log("🔍 Verifying signed message...");
+
+        let params: VerifySignedMessageParams = parse_params(params_json)?;
+
+        let (content, header, signatures) = self.verify_signed(&params)?;
+        let content = String::from_utf8(content).map_err(|e| {
+            Error::BadParams(format!("Signed message is not valid UTF-8, use verify_signed_bytes: {}", e))
+        })?;
+        let content = match header.as_ref().map(|header| header.mode()) {
+            None | Some(DataMode::Binary) => content,
+            _ => text_from_crlf(content),
+        };
+        let result = SignedMessageResult {
+            is_valid: all_valid(&signatures),
+            content,
+            signatures,
+        };
+
+        serde_json::to_string(&result)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    // Like `verify_signed_message`, with the content exactly as it was signed
+    // and its literal data metadata, so binary payloads come through intact
+    pub fn verify_signed_bytes(&self, params_json: String) -> Result<VerifiedData, Error> {
+        log("🔍 Verifying signed data...");
+
+        let params: VerifySignedMessageParams = parse_params(params_json)?;
+
+        let (data, header, signatures) = self.verify_signed(&params)?;
+        let literal = match &header {
+            Some(header) => literal_metadata(header),
+            None => LiteralMetadata {
+                format: Some(LiteralFormat::Utf8),
+                ..LiteralMetadata::default()
+            },
+        };
+
+        Ok(VerifiedData {
+            data,
+            is_valid: all_valid(&signatures),
+            signatures: serde_json::to_string(&signatures)
+                .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))?,
+            filename: literal.filename,
+            modified_at: literal.modified_at,
+            format: literal.format.unwrap_or(LiteralFormat::Binary).name().to_string(),
+        })
+    }
+
+    pub fn verify_message(&self, params_json: String) -> Result<String, Error> {
+        log("🔍 Verifying signature with post-quantum cryptography...");
+    
+        let params: VerifyParams = parse_params(params_json)?;
+
+        let result = self.verify(&params, params.message.as_bytes())?;
+    
+        serde_json::to_string(&result)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    pub fn verify_bytes(&self, params_json: String, data: &[u8]) -> Result<String, Error> {
+        log("🔍 Verifying signature over data with post-quantum cryptography...");
+
+        let params: VerifyParams = parse_params(params_json)?;
+
+        let result = self.verify(&params, data)?;
+
+        serde_json::to_string(&result)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    pub fn export_public_key(&self, key_id: &str) -> Result<String, Error> {
+        let public_key = find_key(&self.public_keys, key_id)
+            .ok_or_else(|| Error::KeyNotFound(format!("Public key not found: {}", key_id)))?;
+
+        let public_key_bytes = public_key
+            .to_bytes()
+            .map_err(|e| Error::Internal(format!("Failed to serialize public key: {}", e)))?;
+        armor_packets(BlockType::PublicKey, &public_key_bytes)
+    }
+
+    // The secret key is exported as stored, i.e. still protected by its passphrase
+    pub fn export_secret_key(&self, key_id: &str) -> Result<String, Error> {
+        let secret_key = find_key(&self.secret_keys, key_id)
+            .ok_or_else(|| Error::KeyNotFound(format!("Private key not found: {}", key_id)))?;
+
+        let private_key_bytes = secret_key
+            .to_bytes()
+            .map_err(|e| Error::Internal(format!("Failed to serialize private key: {}", e)))?;
+        armor_packets(BlockType::PrivateKey, &private_key_bytes)
+    }
+
+    pub fn list_keys(&self) -> Result<String, Error> {
+        let mut keys: Vec<KeyInfo> = self
+            .public_keys
+            .iter()
+            .map(|(fingerprint, public_key)| {
+                let mut info = key_info(public_key);
+                let secret_key = self.secret_keys.get(fingerprint);
+                info.has_secret_key = secret_key.is_some();
+                info.memory_only = secret_key.is_some_and(|secret_key| !is_protected(secret_key));
+                info
+            })
+            .collect();
+        keys.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+
+        serde_json::to_string(&keys)
+            .map_err(|e| Error::Internal(format!("Serialization error: {}", e)))
+    }
+
+    // Removes the certificate and, if present, the secret key
+    pub fn delete_key(&mut self, key_id: &str) -> Result<(), Error> {
+        let public_key = find_key(&self.public_keys, key_id)
+            .ok_or_else(|| Error::KeyNotFound(format!("Key not found: {}", key_id)))?;
+        let (_, fingerprint) = key_identifiers(&public_key.primary_key);
+
+        self.public_keys.remove(&fingerprint);
+        // Dropping the secret key wipes it: rPGP zeroizes unlocked secret
+        // parameters on drop, and the encrypted packets hold no usable material
+        drop(self.secret_keys.remove(&fingerprint));
+        self.stored_secret_keys.remove(&fingerprint);
+        self.store.remove(&fingerprint)?;
+
+        log(&format!("🗑️ Deleted key {}", fingerprint));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn generate(keyring: &mut Keyring, passphrase: &str) -> String {
+        generate_with(keyring, passphrase, "Ed25519", "X25519").unwrap()
+    }
+
+    // Cheap Argon2 parameters, the defaults take seconds in a debug build
+    fn generate_with(
+        keyring: &mut Keyring,
+        passphrase: &str,
+        signing_algorithm: &str,
+        encryption_algorithm: &str,
+    ) -> Result<String, Error> {
+        let result = keyring.generate_key_pair(
+            json!({
+                "user_id": "Alice <alice@example.org>",
+                "passphrase": passphrase,
+                "s2k": { "time_cost": 1, "parallelism": 1, "memory_cost_kib": 8 },
+                "signing_algorithm": signing_algorithm,
+                "encryption_algorithm": encryption_algorithm,
+            })
+            .to_string(),
+        )?;
+        let result: Value = serde_json::from_str(&result).unwrap();
+        Ok(result["fingerprint"].as_str().unwrap().to_string())
+    }
+
+    fn decrypt(keyring: &Keyring, key_id: &str, passphrase: &str, ciphertext: &str) -> Result<String, Error> {
+        keyring.decrypt_message(
+            json!({ "private_key_id": key_id, "passphrase": passphrase, "ciphertext": ciphertext }).to_string(),
+        )
+    }
+
+    #[test]
+    fn reports_distinct_decryption_failures() {
+        let mut keyring = Keyring::new();
+        let recipient = generate(&mut keyring, "secret");
+        let other = generate(&mut keyring, "secret");
+
+        let ciphertext = keyring
+            .encrypt_message(json!({ "recipient_key_ids": [recipient], "plaintext": "hello" }).to_string())
+            .unwrap();
+        let decrypted: Value =
+            serde_json::from_str(&decrypt(&keyring, &recipient, "secret", &ciphertext).unwrap()).unwrap();
+        assert_eq!(decrypted["plaintext"], "hello");
+
+        let wrong_key = decrypt(&keyring, &other, "secret", &ciphertext).unwrap_err();
+        assert_eq!(wrong_key.code(), "KEY_NOT_FOUND");
+
+        let wrong_passphrase = decrypt(&keyring, &recipient, "not the passphrase", &ciphertext).unwrap_err();
+        assert_eq!(wrong_passphrase.code(), "WRONG_PASSPHRASE");
+
+        // Flipping the last byte breaks the final AEAD authentication tag
+        let mut packets = armor::decode_as(&ciphertext, BlockType::Message).unwrap();
+        *packets.last_mut().unwrap() ^= 0x01;
+        let tampered = armor_packets(BlockType::Message, &packets).unwrap();
+        let tampered = decrypt(&keyring, &recipient, "secret", &tampered).unwrap_err();
+        assert_eq!(tampered.code(), "INTEGRITY_FAILURE");
+    }
+
+    // Sign "hello" detached, inline and cleartext, and check each signature
+    // verifies as made by the primary key with the expected algorithm
+    fn assert_signs(keyring: &Keyring, fingerprint: &str, algorithm: &str) {
+        let sign_params = json!({ "private_key_id": fingerprint, "passphrase": "secret", "message": "hello" });
+        let signature = keyring.create_detached_signature(sign_params.to_string()).unwrap();
+        let inline = keyring.sign_message(sign_params.to_string()).unwrap();
+        let cleartext = keyring.sign_cleartext_message(sign_params.to_string()).unwrap();
+
+        let detached: Value = serde_json::from_str(
+            &keyring
+                .verify_message(json!({ "message": "hello", "signature": signature }).to_string())
+                .unwrap(),
+        )
+        .unwrap();
+        let inline: Value = serde_json::from_str(
+            &keyring
+                .verify_signed_message(json!({ "signed_message": inline }).to_string())
+                .unwrap(),
+        )
+        .unwrap();
+        let cleartext: Value = serde_json::from_str(
+            &keyring
+                .verify_cleartext_message(json!({ "signed_message": cleartext }).to_string())
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(inline["content"], "hello");
+        assert_eq!(cleartext["content"], "hello");
+        for result in [&detached, &inline["signatures"][0], &cleartext["signatures"][0]] {
+            assert_eq!(result["is_valid"], true, "{}", result);
+            assert_eq!(result["signer_fingerprint"], fingerprint);
+            assert_eq!(result["public_key_algorithm"], algorithm);
+        }
+    }
+
+    // Records shared with the test, to check what reached the store
+    #[derive(Clone, Default)]
+    struct SharedStore(Rc<RefCell<HashMap<String, StoredKey>>>);
+
+    impl KeyStore for SharedStore {
+        fn put(&mut self, record: StoredKey) -> Result<(), Error> {
+            self.0.borrow_mut().insert(record.fingerprint.clone(), record);
+            Ok(())
+        }
+
+        fn remove(&mut self, fingerprint: &str) -> Result<(), Error> {
+            self.0.borrow_mut().remove(fingerprint);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn deletes_keys_everywhere() {
+        let store = SharedStore::default();
+        let mut keyring = Keyring::with_store(Box::new(store.clone()));
+        let fingerprint = generate(&mut keyring, "secret");
+        let kept = generate(&mut keyring, "secret");
+        assert!(store.0.borrow().contains_key(&fingerprint));
+
+        let keys: Value = serde_json::from_str(&keyring.list_keys().unwrap()).unwrap();
+        let key = keys.as_array().unwrap().iter().find(|key| key["fingerprint"] == fingerprint).unwrap();
+        let key_id = key["key_id"].as_str().unwrap().to_lowercase();
+        keyring.delete_key(&format!("0x{}", key_id)).unwrap();
+
+        let keys: Value = serde_json::from_str(&keyring.list_keys().unwrap()).unwrap();
+        assert_eq!(keys.as_array().unwrap().len(), 1);
+        assert_eq!(keys[0]["fingerprint"], kept);
+        assert_eq!(store.0.borrow().keys().collect::<Vec<_>>(), [&kept]);
+        for id in [&fingerprint, &key_id] {
+            assert!(find_key(&keyring.public_keys, id).is_none());
+            assert!(find_key(&keyring.secret_keys, id).is_none());
+            assert_eq!(keyring.export_secret_key(id).unwrap_err().code(), "KEY_NOT_FOUND");
+            assert_eq!(keyring.export_public_key(id).unwrap_err().code(), "KEY_NOT_FOUND");
+        }
+        assert!(!keyring.stored_secret_keys.contains_key(&fingerprint));
+        assert_eq!(keyring.delete_key(&fingerprint).unwrap_err().code(), "KEY_NOT_FOUND");
+    }
+
+    #[test]
+    fn round_trips_composite_encryption() {
+        for (signing_algorithm, encryption_algorithm) in
+            [("ML-DSA-65+Ed25519", "ML-KEM-768+X25519"), ("ML-DSA-87+Ed448", "ML-KEM-1024+X448")]
+        {
+            let mut keyring = Keyring::new();
+            let fingerprint = generate_with(&mut keyring, "secret", signing_algorithm, encryption_algorithm).unwrap();
+
+            let keys: Value = serde_json::from_str(&keyring.list_keys().unwrap()).unwrap();
+            assert_eq!(keys[0]["algorithm"], signing_algorithm);
+            assert_eq!(keys[0]["subkeys"][0]["algorithm"], encryption_algorithm);
+
+            let ciphertext = keyring
+                .encrypt_message(json!({ "recipient_key_ids": [fingerprint], "plaintext": "hello" }).to_string())
+                .unwrap();
+            let decrypted: Value =
+                serde_json::from_str(&decrypt(&keyring, &fingerprint, "secret", &ciphertext).unwrap()).unwrap();
+            assert_eq!(decrypted["plaintext"], "hello");
+        }
+    }
+
+    #[test]
+    fn signs_with_composite_ml_dsa_keys() {
+        for signing_algorithm in ["ML-DSA-65+Ed25519", "ML-DSA-87+Ed448"] {
+            let mut keyring = Keyring::new();
+            let fingerprint = generate_with(&mut keyring, "secret", signing_algorithm, "ML-KEM-768+X25519").unwrap();
+            assert_signs(&keyring, &fingerprint, signing_algorithm);
+        }
+    }
+
+    // The fast variant, the small ones take seconds per signature
+    #[test]
+    fn signs_with_slh_dsa_keys() {
+        let mut keyring = Keyring::new();
+        let fingerprint = generate_with(&mut keyring, "secret", "SLH-DSA-SHAKE-128f", "ML-KEM-768+X25519").unwrap();
+        assert_signs(&keyring, &fingerprint, "SLH-DSA-SHAKE-128f");
+    }
+
+    #[test]
+    fn rejects_standalone_ml_dsa_and_ml_kem() {
+        let mut keyring = Keyring::new();
+        for (signing_algorithm, encryption_algorithm) in
+            [("ML-DSA-87", "ML-KEM-1024+X448"), ("ML-DSA-87+Ed448", "ML-KEM-1024")]
+        {
+            let err = generate_with(&mut keyring, "secret", signing_algorithm, encryption_algorithm).unwrap_err();
+            assert_eq!(err.code(), "UNSUPPORTED_ALGORITHM");
+        }
+        assert_eq!(keyring.list_keys().unwrap(), "[]");
+    }
+
+    #[test]
+    fn round_trips_literal_metadata() {
+        let mut keyring = Keyring::new();
+        let fingerprint = generate(&mut keyring, "secret");
+
+        let ciphertext = keyring
+            .encrypt_bytes(
+                json!({
+                    "recipient_key_ids": [fingerprint],
+                    "filename": "report.pdf",
+                    "modified_at": "2024-05-01T12:30:00.000Z",
+                    "format": "binary",
+                })
+                .to_string(),
+                &[0x25, 0x50, 0x44, 0x46, 0xff, 0x00],
+            )
+            .unwrap();
+        let decrypted = keyring
+            .decrypt_bytes(
+                json!({ "private_key_id": fingerprint, "passphrase": "secret", "ciphertext": ciphertext })
+                    .to_string(),
+            )
+            .ok()
+            .unwrap();
+        assert_eq!(decrypted.data, [0x25, 0x50, 0x44, 0x46, 0xff, 0x00]);
+        assert_eq!(decrypted.filename.as_deref(), Some("report.pdf"));
+        assert_eq!(decrypted.modified_at.as_deref(), Some("2024-05-01T12:30:00.000Z"));
+        assert_eq!(decrypted.format, "binary");
+
+        let ciphertext = keyring
+            .encrypt_message(
+                json!({
+                    "recipient_key_ids": [fingerprint],
+                    "plaintext": "line one\nline two",
+                    "format": "for_your_eyes_only",
+                })
+                .to_string(),
+            )
+            .unwrap();
+        let decrypted: Value =
+            serde_json::from_str(&decrypt(&keyring, &fingerprint, "secret", &ciphertext).unwrap()).unwrap();
+        assert_eq!(decrypted["plaintext"], "line one\nline two");
+        assert_eq!(decrypted["filename"], Value::Null);
+        assert_eq!(decrypted["format"], "for_your_eyes_only");
+    }
+
+    #[test]
+    fn verifies_binary_signed_data() {
+        let mut keyring = Keyring::new();
+        let fingerprint = generate(&mut keyring, "secret");
+
+        let signed = keyring
+            .sign_bytes(
+                json!({ "private_key_id": fingerprint, "passphrase": "secret", "filename": "data.bin" }).to_string(),
+                &[0xff, 0xfe, 0x00],
+            )
+            .unwrap();
+        let verified = keyring
+            .verify_signed_bytes(json!({ "signed_message": signed }).to_string())
+            .ok()
+            .unwrap();
+        assert!(verified.is_valid);
+        assert_eq!(verified.data, [0xff, 0xfe, 0x00]);
+        assert_eq!(verified.filename.as_deref(), Some("data.bin"));
+        assert_eq!(verified.format, "binary");
+    }
+
+    #[test]
+    fn keeps_the_stored_key_protected_when_the_passphrase_is_removed() {
+        let mut keyring = Keyring::new();
+        let fingerprint = generate(&mut keyring, "secret");
+
+        keyring
+            .change_passphrase(
+                json!({ "private_key_id": fingerprint, "old_passphrase": "secret", "new_passphrase": "" }).to_string(),
+            )
+            .unwrap();
+
+        let keys: Value = serde_json::from_str(&keyring.list_keys().unwrap()).unwrap();
+        assert_eq!(keys[0]["memory_only"], false);
+        let sign = |passphrase: &str| {
+            keyring.sign_message(
+                json!({ "private_key_id": fingerprint, "passphrase": passphrase, "message": "hi" }).to_string(),
+            )
+        };
+        assert_eq!(sign("").unwrap_err().code(), "WRONG_PASSPHRASE");
+        assert!(sign("secret").is_ok());
+    }
+
+    #[test]
+    fn drops_third_party_certifications_from_imported_secret_keys() {
+        let build = |user_id: &str| {
+            build_secret_key(
+                user_id,
+                "",
+                &crate::S2kOptions::default(),
+                crate::SigningAlgorithm::Ed25519,
+                crate::EncryptionAlgorithm::X25519,
+            )
+            .unwrap()
+        };
+        let mut alice = build("Alice <alice@example.org>");
+        let bob = build("Bob <bob@example.org>");
+
+        let user_id = alice.details.users[0].id.clone();
+        let certification = user_id
+            .sign_third_party(
+                rand::thread_rng(),
+                &bob.primary_key,
+                &Password::empty(),
+                &alice.primary_key.public_key(),
+                pgp::packet::SignatureType::CertGeneric,
+            )
+            .unwrap();
+        alice.details.users[0].signatures.extend(certification.signatures);
+        let armored = armor_packets(BlockType::PrivateKey, &alice.to_bytes().unwrap()).unwrap();
+
+        let mut keyring = Keyring::new();
+        let info: Value = serde_json::from_str(
+            &keyring
+                .import_secret_key(json!({ "private_key_armored": armored }).to_string())
+                .unwrap(),
+        )
+        .unwrap();
+        let fingerprint = info["fingerprint"].as_str().unwrap();
+
+        let exported = parse_secret_key(&keyring.export_secret_key(fingerprint).unwrap()).unwrap();
+        assert_eq!(exported.details.users[0].signatures.len(), 1);
+        let certificate = parse_public_key(&keyring.export_public_key(fingerprint).unwrap()).unwrap();
+        assert_eq!(exported.details, certificate.details);
+    }
+}
@@ -0,0 +1,190 @@
+// Persistence for keyrings. A `KeyStore` receives a record every time a key is
+// added or changed and a removal when it is deleted. Records only ever hold
+// the armored certificate and, when every secret packet is passphrase
+// protected, the armored secret key; unprotected secrets stay in memory and a
+// protected copy stored earlier is left in place.
+
+use std::collections::HashMap;
+
+use js_sys::{Array, Promise};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbFactory, IdbObjectStore, IdbRequest, IdbTransaction, IdbTransactionMode};
+
+use crate::error::Error;
+use crate::log;
+
+const DB_VERSION: u32 = 1;
+const OBJECT_STORE: &str = "keys";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredKey {
+    pub fingerprint: String,
+    pub public_key_armored: String,
+    pub private_key_armored: Option<String>,
+}
+
+pub trait KeyStore {
+    fn put(&mut self, record: StoredKey) -> Result<(), Error>;
+    fn remove(&mut self, fingerprint: &str) -> Result<(), Error>;
+    // Writes made since the last call that may not have been committed yet,
+    // for stores that commit in the background
+    fn take_pending(&mut self) -> Vec<PendingWrite> {
+        Vec::new()
+    }
+}
+
+// A write on its own IndexedDB transaction, settled by the transaction's
+// complete or abort event
+pub struct PendingWrite {
+    transaction: IdbTransaction,
+    done: Promise,
+}
+
+// Wait for pending writes in order, failing on the first one that aborted
+pub async fn committed(pending: Vec<PendingWrite>) -> Result<(), Error> {
+    for write in pending {
+        if JsFuture::from(write.done).await.is_err() {
+            let reason = write
+                .transaction
+                .error()
+                .map(|e| e.message())
+                .unwrap_or_else(|| "transaction aborted".to_string());
+            log(&format!("❌ Failed to write to IndexedDB: {}", reason));
+            return Err(Error::Internal(format!("Failed to write to IndexedDB: {}", reason)));
+        }
+    }
+    Ok(())
+}
+
+// Keeps records for the lifetime of the keyring only
+#[derive(Default)]
+pub struct MemoryKeyStore {
+    records: HashMap<String, StoredKey>,
+}
+
+impl KeyStore for MemoryKeyStore {
+    fn put(&mut self, record: StoredKey) -> Result<(), Error> {
+        self.records.insert(record.fingerprint.clone(), record);
+        Ok(())
+    }
+
+    fn remove(&mut self, fingerprint: &str) -> Result<(), Error> {
+        self.records.remove(fingerprint);
+        Ok(())
+    }
+}
+
+// Records in an IndexedDB object store, keyed by fingerprint. Writes are
+// queued on their own transaction and commit in the background; they are
+// kept as pending until someone waits for them with `committed`
+pub struct IndexedDbKeyStore {
+    db: IdbDatabase,
+    pending: Vec<PendingWrite>,
+}
+
+impl IndexedDbKeyStore {
+    // Open (and on first use create) the database, returning the records it holds
+    pub async fn open(name: &str) -> Result<(IndexedDbKeyStore, Vec<StoredKey>), Error> {
+        let factory: IdbFactory = js_sys::Reflect::get(&js_sys::global(), &"indexedDB".into())?
+            .dyn_into()
+            .map_err(|_| Error::Internal("IndexedDB is not available in this environment".to_string()))?;
+
+        let open_request = factory.open_with_u32(name, DB_VERSION)?;
+        let on_upgrade = Closure::once({
+            let open_request = open_request.clone();
+            move || {
+                let Ok(db) = open_request.result().and_then(|db| db.dyn_into::<IdbDatabase>()) else {
+                    return;
+                };
+                if !db.object_store_names().contains(OBJECT_STORE) {
+                    if let Err(e) = db.create_object_store(OBJECT_STORE) {
+                        log(&format!("❌ Failed to create key store: {:?}", e));
+                    }
+                }
+            }
+        });
+        open_request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+
+        let db = request_result(open_request.unchecked_ref::<IdbRequest>()).await;
+        // Any upgrade has run by now, so the closure can be released
+        open_request.set_onupgradeneeded(None);
+        drop(on_upgrade);
+        let store = IndexedDbKeyStore {
+            db: db?.dyn_into()?,
+            pending: Vec::new(),
+        };
+
+        let records = request_result(&store.object_store(IdbTransactionMode::Readonly)?.get_all()?).await?;
+        let records = Array::from(&records)
+            .iter()
+            .filter_map(|record| match serde_wasm_bindgen::from_value(record) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    log(&format!("⚠️ Skipping unreadable key store record: {}", e));
+                    None
+                }
+            })
+            .collect();
+
+        Ok((store, records))
+    }
+
+    fn object_store(&self, mode: IdbTransactionMode) -> Result<IdbObjectStore, Error> {
+        Ok(self
+            .db
+            .transaction_with_str_and_mode(OBJECT_STORE, mode)?
+            .object_store(OBJECT_STORE)?)
+    }
+
+    // The object store on a new read-write transaction, tracked as pending.
+    // The transaction's events settle the promise directly, so no closure
+    // has to outlive the call
+    fn write(&mut self) -> Result<IdbObjectStore, Error> {
+        let transaction = self
+            .db
+            .transaction_with_str_and_mode(OBJECT_STORE, IdbTransactionMode::Readwrite)?;
+        let done = Promise::new(&mut |resolve, reject| {
+            transaction.set_oncomplete(Some(&resolve));
+            transaction.set_onabort(Some(&reject));
+        });
+        let object_store = transaction.object_store(OBJECT_STORE)?;
+        self.pending.push(PendingWrite { transaction, done });
+        Ok(object_store)
+    }
+}
+
+impl KeyStore for IndexedDbKeyStore {
+    fn put(&mut self, record: StoredKey) -> Result<(), Error> {
+        let value = serde_wasm_bindgen::to_value(&record).map_err(JsValue::from)?;
+        self.write()?
+            .put_with_key(&value, &JsValue::from_str(&record.fingerprint))?;
+        Ok(())
+    }
+
+    fn remove(&mut self, fingerprint: &str) -> Result<(), Error> {
+        self.write()?.delete(&JsValue::from_str(fingerprint))?;
+        Ok(())
+    }
+
+    fn take_pending(&mut self) -> Vec<PendingWrite> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+// Wait for an IndexedDB request to finish and return its result. The
+// request's events settle the promise directly, so no closure is left behind
+// for the event that does not fire
+async fn request_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let done = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    match JsFuture::from(done).await {
+        Ok(_) => request.result(),
+        Err(_) => Err(request.error()?.map(JsValue::from).unwrap_or(JsValue::UNDEFINED)),
+    }
+}
+